@@ -20,6 +20,24 @@ struct VertexVelocity {
     velocity: [f32; 3],
 }
 
+// Per-vertex inverse mass: 0 pins the vertex in place (springs still pull
+// on it, but the integrate passes never move it), 1/mass lets it fall
+// freely. Kept alongside VertexVelocity rather than folded into it so
+// pinning can be toggled independently of the physical mass.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct VertexPin {
+    inv_mass: f32,
+}
+
+// Which vertices create_cloth_mesh pins in place.
+#[derive(Copy, Clone, PartialEq)]
+enum PinMode {
+    None,    // the original drop test: everything falls freely
+    Curtain, // pin the whole top row
+    Flag,    // pin only the two top corners
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct Spring {
@@ -29,6 +47,25 @@ struct Spring {
     stiffness: f32,
 }
 
+// A single 3-component accumulator used by the implicit solver's CG loop
+// (force, residual r, search direction p, Ap). Kept distinct from
+// VertexVelocity so the solver's scratch buffers are self-documenting.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CGVec {
+    v: [f32; 3],
+}
+
+// Mirrors CGScalars in the CG wgsl shaders: fixed-point atomic accumulators
+// for the r.r and p.Ap dot products (WGSL has no atomic<f32>).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CGScalars {
+    rr: i32,
+    pap: i32,
+    rr_new: i32,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct ComputeData {
@@ -47,6 +84,27 @@ struct ComputeData {
     sphere_position_x: f32,
     sphere_position_y: f32,
     sphere_position_z: f32,
+    //implicit solver
+    integration_mode: f32,
+    cg_iterations: f32,
+    //self-collision grid
+    grid_cell_size: f32,
+    self_collision_radius: f32,
+    //heightfield terrain
+    terrain_origin_x: f32,
+    terrain_origin_z: f32,
+    terrain_cell_size: f32,
+    terrain_width: f32,
+    terrain_depth: f32,
+    terrain_thickness: f32,
+    terrain_friction: f32,
+    //wind
+    nb_cloth_triangles: f32,
+    wind_x: f32,
+    wind_y: f32,
+    wind_z: f32,
+    air_density: f32,
+    drag_coefficient: f32,
 }
 
 // --------   PARAMETERS OF THE SIMULATION   --------
@@ -65,14 +123,46 @@ const CLOTH_FALL_HEIGHT: f32 = (CLOTH_WIDTH as f32) / 3.0;
 const STRUCTURAL_STIFFNESS: f32 = 200.0;
 const SHEAR_STIFFNESS: f32 = 140.0;
 const BEND_STIFFNESS: f32 = 70.0;
+const PIN_MODE: PinMode = PinMode::Curtain;
 //SPHERE
 const SPHERE_RADIUS: f32 = (CLOTH_WIDTH as f32) / 7.0;
 const SPHERE_POSITION_X: f32 = 0.0;
 const SPHERE_POSITION_Y: f32 = 0.0;
 const SPHERE_POSITION_Z: f32 = 0.0;
+//INTEGRATION (explicit mass-spring vs. implicit backward-Euler + CG)
+const INTEGRATION_MODE_EXPLICIT: f32 = 0.0;
+const INTEGRATION_MODE_IMPLICIT: f32 = 1.0;
+const INTEGRATION_MODE: f32 = INTEGRATION_MODE_IMPLICIT;
+const CG_ITERATIONS: u32 = 15;
+//SELF-COLLISION (uniform spatial hash grid)
+const NB_SELF_COLLISION_BUCKETS: u32 = 2048; // keep in sync with the *.wgsl self-collision shaders
+const GRID_CELL_SIZE: f32 = 1.0; // ~ the cloth's rest edge length
+const SELF_COLLISION_RADIUS: f32 = 0.5;
+//TERRAIN (heightfield collider)
+const TERRAIN_WIDTH: u32 = 20; // grid points along x
+const TERRAIN_DEPTH: u32 = 20; // grid points along z
+const TERRAIN_CELL_SIZE: f32 = 2.0;
+const TERRAIN_ORIGIN_X: f32 = -((TERRAIN_WIDTH - 1) as f32) * TERRAIN_CELL_SIZE / 2.0;
+const TERRAIN_ORIGIN_Z: f32 = -((TERRAIN_DEPTH - 1) as f32) * TERRAIN_CELL_SIZE / 2.0;
+const TERRAIN_HEIGHT_AMPLITUDE: f32 = 1.0;
+const TERRAIN_THICKNESS: f32 = 0.05;
+const TERRAIN_FRICTION: f32 = 0.1;
+//LIGHTING / SHADOWS (single directional light)
+const LIGHT_DIRECTION: (f32, f32, f32) = (-0.4, -1.0, -0.3); // normalized copy hardcoded in cloth/sphere shaders
+const LIGHT_DISTANCE: f32 = (CLOTH_WIDTH as f32) * 2.0;
+const SHADOW_MAP_SIZE: u32 = 2048;
+//WIND (aerodynamic drag on cloth triangles)
+const NB_CLOTH_TRIANGLES: f32 = (2 * (CLOTH_WIDTH - 1).pow(2)) as f32; // 2 triangles/quad, unique winding only (see cloth_wind_triangle_indices)
+const WIND_X: f32 = 2.5;
+const WIND_Y: f32 = 0.0;
+const WIND_Z: f32 = 0.5;
+const AIR_DENSITY: f32 = 1.225;
+const DRAG_COEFFICIENT: f32 = 1.0;
+const GUST_AMPLITUDE: f32 = 0.4; // fraction of the base wind the gust adds/removes
+const GUST_FREQUENCY: f32 = 0.8; // radians/sec
 // ==================================================
 
-fn create_cloth_mesh(width: u16, altitude: f32) -> (Vec<Vertex>, Vec<u16>, Vec<VertexVelocity>, Vec<Spring>) {       //creates a cloth mesh of vertices of width x width
+fn create_cloth_mesh(width: u16, altitude: f32, pin_mode: PinMode) -> (Vec<Vertex>, Vec<u16>, Vec<VertexVelocity>, Vec<Spring>, Vec<VertexPin>) {       //creates a cloth mesh of vertices of width x width
 
     // VERTICES
     let mut vertices = Vec::new();
@@ -109,6 +199,19 @@ fn create_cloth_mesh(width: u16, altitude: f32) -> (Vec<Vertex>, Vec<u16>, Vec<V
         velocities.push(VertexVelocity {velocity: [0.0, 0.0, 0.0]})
     }
 
+    //PINNING (the top row is z == 0)
+    let mut pins = Vec::new();
+    for z in 0..height {
+        for x in 0..width {
+            let pinned = match pin_mode {
+                PinMode::None => false,
+                PinMode::Curtain => z == 0,
+                PinMode::Flag => z == 0 && (x == 0 || x == width - 1),
+            };
+            pins.push(VertexPin { inv_mass: if pinned { 0.0 } else { 1.0 / CLOTH_VERTEX_MASS } });
+        }
+    }
+
     //SPRINGS
     let mut springs = Vec::new();
     for i in 0..NB_CLOTH_VERTICES {
@@ -147,7 +250,23 @@ fn create_cloth_mesh(width: u16, altitude: f32) -> (Vec<Vertex>, Vec<u16>, Vec<V
         }
     }
     // println!("number of springs: {}", springs.len());
-    (vertices, indices, velocities, springs)
+    (vertices, indices, velocities, springs, pins)
+}
+
+// Builds a row-major (x then z) heightfield of `width * depth` samples,
+// rolling hills from a couple of sine waves so the terrain collider has
+// something other than a flat plane to drape the cloth over.
+fn create_terrain_heights(width: u32, depth: u32, amplitude: f32) -> Vec<f32> {
+    let mut heights = Vec::new();
+    for z in 0..depth {
+        for x in 0..width {
+            let u = x as f32;
+            let v = z as f32;
+            let height = amplitude * 0.5 * ((u * 0.5).sin() + (v * 0.5).cos());
+            heights.push(height);
+        }
+    }
+    heights
 }
 
 struct MyApp {
@@ -155,24 +274,115 @@ struct MyApp {
     //cloth
     cloth_diffuse_bind_group: wgpu::BindGroup,
     cloth_pipeline: wgpu::RenderPipeline,
-    cloth_vertex_buffer: wgpu::Buffer,
+    // Ping-pong: two full position/velocity sets. `current_set` is the one
+    // most recently finished (and the one the renderer reads this frame);
+    // each update() writes into the other and then flips `current_set`.
+    cloth_vertex_buffers: [wgpu::Buffer; 2],
+    cloth_vertex_velocity_buffers: [wgpu::Buffer; 2],
+    current_set: usize,
     cloth_index_buffer: wgpu::Buffer,
     nb_cloth_indices: usize,
     //compute
+    compute_copy_pipeline: wgpu::ComputePipeline,
+    compute_copy_src_bind_groups: [wgpu::BindGroup; 2],
+    compute_copy_dst_bind_groups: [wgpu::BindGroup; 2],
     compute_pipeline: wgpu::ComputePipeline,
     compute_springs_pipeline: wgpu::ComputePipeline,
-    compute_vertices_bind_group: wgpu::BindGroup,
-    compute_vertex_velocities_bind_group: wgpu::BindGroup,
+    // Read-write vertices/velocities(+pins+terrain) for set [i], used by
+    // whichever pass is treating set i as the frame's "next" buffer
+    // (self-collision, the explicit integrator, the implicit integrator).
+    compute_vertices_bind_groups: [wgpu::BindGroup; 2],
+    compute_vertex_velocities_bind_groups: [wgpu::BindGroup; 2],
+    // Read-only vertices for set [i], used by passes that only ever read
+    // positions out of the frame's "current" (or already-copied) buffer.
+    compute_springs_vertices_bind_groups: [wgpu::BindGroup; 2],
     compute_springs_bind_group: wgpu::BindGroup,
+    // Velocities-only group(1) for passes whose shader only declares binding
+    // 0 there (pins/terrain aren't part of their group layout, unlike
+    // compute.wgsl/compute_cg_integrate.wgsl), so the full
+    // compute_vertex_velocities_bind_groups isn't bind-group-compatible.
+    compute_springs_velocities_bind_groups: [wgpu::BindGroup; 2],
+    compute_wind_velocities_bind_groups: [wgpu::BindGroup; 2],
+    compute_self_collision_resolve_velocities_bind_groups: [wgpu::BindGroup; 2],
     compute_data_bind_group: wgpu::BindGroup,
     compute_data_buffer: wgpu::Buffer,
     compute_data: ComputeData,
+    //wind
+    compute_wind_pipeline: wgpu::ComputePipeline,
+    compute_wind_vertices_bind_groups: [wgpu::BindGroup; 2],
+    compute_wind_triangles_bind_group: wgpu::BindGroup,
+    wind_phase: f32,
+    //implicit solver (matrix-free conjugate gradient)
+    cg_force_buffer: wgpu::Buffer,
+    cg_r_buffer: wgpu::Buffer,
+    cg_p_buffer: wgpu::Buffer,
+    cg_ap_buffer: wgpu::Buffer,
+    cg_delta_v_buffer: wgpu::Buffer,
+    cg_scalars_buffer: wgpu::Buffer,
+    cg_setup_pipeline: wgpu::ComputePipeline,
+    cg_setup_force_bind_group: wgpu::BindGroup,
+    cg_setup_data_bind_group: wgpu::BindGroup,
+    cg_spring_forces_pipeline: wgpu::ComputePipeline,
+    cg_spring_forces_vertices_bind_groups: [wgpu::BindGroup; 2],
+    cg_spring_forces_force_bind_group: wgpu::BindGroup,
+    cg_spring_forces_springs_bind_group: wgpu::BindGroup,
+    // h^2*(df/dx)*v term folded into the RHS alongside h*f, see
+    // compute_cg_rhs_velocity_springs.wgsl.
+    cg_velocity_term_buffer: wgpu::Buffer,
+    cg_rhs_velocity_setup_pipeline: wgpu::ComputePipeline,
+    cg_rhs_velocity_setup_term_bind_group: wgpu::BindGroup,
+    cg_rhs_velocity_setup_data_bind_group: wgpu::BindGroup,
+    cg_rhs_velocity_springs_pipeline: wgpu::ComputePipeline,
+    cg_rhs_velocity_springs_vertices_bind_groups: [wgpu::BindGroup; 2],
+    cg_rhs_velocity_springs_data_bind_group: wgpu::BindGroup,
+    cg_rhs_velocity_springs_velocities_springs_bind_groups: [wgpu::BindGroup; 2],
+    cg_rhs_velocity_springs_term_bind_group: wgpu::BindGroup,
+    cg_rhs_pipeline: wgpu::ComputePipeline,
+    cg_rhs_force_bind_group: wgpu::BindGroup,
+    cg_rhs_vectors_bind_group: wgpu::BindGroup,
+    cg_rhs_velocity_term_bind_group: wgpu::BindGroup,
+    cg_matvec_init_pipeline: wgpu::ComputePipeline,
+    cg_matvec_init_p_bind_group: wgpu::BindGroup,
+    cg_matvec_init_ap_bind_group: wgpu::BindGroup,
+    cg_matvec_init_scalars_bind_group: wgpu::BindGroup,
+    cg_matvec_springs_pipeline: wgpu::ComputePipeline,
+    cg_matvec_springs_vertices_bind_groups: [wgpu::BindGroup; 2],
+    cg_matvec_springs_p_springs_bind_group: wgpu::BindGroup,
+    cg_matvec_springs_ap_bind_group: wgpu::BindGroup,
+    cg_dot_pipeline: wgpu::ComputePipeline,
+    cg_dot_vectors_bind_group: wgpu::BindGroup,
+    cg_dot_scalars_bind_group: wgpu::BindGroup,
+    cg_update_pipeline: wgpu::ComputePipeline,
+    cg_update_delta_v_r_bind_group: wgpu::BindGroup,
+    cg_update_p_ap_bind_group: wgpu::BindGroup,
+    cg_update_scalars_bind_group: wgpu::BindGroup,
+    cg_beta_pipeline: wgpu::ComputePipeline,
+    cg_beta_r_p_bind_group: wgpu::BindGroup,
+    cg_beta_scalars_bind_group: wgpu::BindGroup,
+    cg_integrate_pipeline: wgpu::ComputePipeline,
+    cg_integrate_delta_v_bind_group: wgpu::BindGroup,
+    //self-collision (uniform spatial hash grid)
+    self_collision_heads_buffer: wgpu::Buffer,
+    self_collision_next_buffer: wgpu::Buffer,
+    compute_self_collision_reset_pipeline: wgpu::ComputePipeline,
+    compute_self_collision_reset_heads_bind_group: wgpu::BindGroup,
+    compute_self_collision_hash_pipeline: wgpu::ComputePipeline,
+    compute_self_collision_hash_vertices_bind_groups: [wgpu::BindGroup; 2],
+    compute_self_collision_hash_grid_bind_group: wgpu::BindGroup,
+    compute_self_collision_resolve_pipeline: wgpu::ComputePipeline,
+    compute_self_collision_resolve_grid_bind_group: wgpu::BindGroup,
     //sphere
     sphere_diffuse_bind_group: wgpu::BindGroup,
     sphere_pipeline: wgpu::RenderPipeline,
     sphere_vertex_buffer: wgpu::Buffer,
     sphere_index_buffer: wgpu::Buffer,
     nb_sphere_indices: usize,
+    //shadows
+    shadow_pipeline: wgpu::RenderPipeline,
+    shadow_texture: wgpu::Texture,
+    shadow_view: wgpu::TextureView,
+    light_bind_group: wgpu::BindGroup,
+    shadow_bind_group: wgpu::BindGroup,
 }
 
 impl MyApp {
@@ -191,6 +401,121 @@ impl MyApp {
 
         let (_camera_buffer, camera_bind_group) = camera.create_camera_bind_group(context);
 
+        //----- SHADOWS -----
+        // The light is treated as a second Camera looking back at the scene
+        // origin, reusing the same CameraUniform layout as the main camera.
+        let light_camera = Camera {
+            eye: (
+                -LIGHT_DIRECTION.0 * LIGHT_DISTANCE,
+                -LIGHT_DIRECTION.1 * LIGHT_DISTANCE,
+                -LIGHT_DIRECTION.2 * LIGHT_DISTANCE,
+            ).into(),
+            target: (0.0, 0.0, 0.0).into(),
+            up: cgmath::Vector3::unit_y(),
+            aspect: 1.0,
+            fovy: 30.0,
+            znear: 0.1,
+            zfar: LIGHT_DISTANCE * 3.0,
+        };
+        let (light_buffer, light_bind_group) = light_camera.create_camera_bind_group(context);
+
+        let shadow_shader_module = context.device().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shadow_shader.wgsl").into()),
+        });
+        let shadow_pipeline_layout = context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&context.camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shadow_pipeline = context.device().create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&shadow_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shadow_shader_module,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let shadow_texture = context.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Map Texture"),
+            size: wgpu::Extent3d { width: SHADOW_MAP_SIZE, height: SHADOW_MAP_SIZE, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let shadow_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let shadow_sampler = context.device().create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let shadow_bind_group_layout = context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+        let shadow_bind_group = context.create_bind_group(
+            "Shadow Bind Group",
+            &shadow_bind_group_layout,
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: light_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&shadow_view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&shadow_sampler) },
+            ],
+        );
+
         //----- CLOTH -----
         let cloth_texture = context.create_srgb_texture("cloth.jpg", include_bytes!("cloth.jpg"));
         let cloth_diffuse_bind_group = create_texture_bind_group(context, &cloth_texture);
@@ -202,16 +527,41 @@ impl MyApp {
             &[
                 &context.texture_bind_group_layout,
                 &context.camera_bind_group_layout,
+                &shadow_bind_group_layout,
             ],
             wgpu::PrimitiveTopology::TriangleList
         );
 
-        let (cloth_vertices, cloth_indices, cloth_vertices_velocities, cloth_springs) = create_cloth_mesh((CLOTH_WIDTH) as u16, CLOTH_FALL_HEIGHT);
+        let (cloth_vertices, cloth_indices, cloth_vertices_velocities, cloth_springs, cloth_vertex_pins) = create_cloth_mesh((CLOTH_WIDTH) as u16, CLOTH_FALL_HEIGHT, PIN_MODE);
 
-        let cloth_vertex_buffer = context.create_buffer(&cloth_vertices, wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE);
+        // Ping-pong: both sets start out identical; the copy pass re-seeds
+        // whichever one is "next" every frame before anything writes to it.
+        let cloth_vertex_buffers = [
+            context.create_buffer(&cloth_vertices, wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE),
+            context.create_buffer(&cloth_vertices, wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE),
+        ];
+        let cloth_vertex_velocity_buffers = [
+            context.create_buffer(&cloth_vertices_velocities, wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE),
+            context.create_buffer(&cloth_vertices_velocities, wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE),
+        ];
         let cloth_index_buffer = context.create_buffer(&cloth_indices, wgpu::BufferUsages::INDEX);
-        let cloth_vertex_velocity_buffer = context.create_buffer(&cloth_vertices_velocities, wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE);
         let cloth_spring_buffer = context.create_buffer(&cloth_springs.as_slice(), wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::UNIFORM);
+        let cloth_vertex_pin_buffer = context.create_buffer(&cloth_vertex_pins, wgpu::BufferUsages::STORAGE);
+
+        // Wind pass needs each physical triangle exactly once, but
+        // cloth_indices draws both windings per quad (so the cloth renders
+        // front and back); take only the first winding's 6 indices out of
+        // every 12-index quad block, then widen to u32 since WGSL storage
+        // buffers have no u16 element type.
+        let cloth_wind_triangle_indices: Vec<u32> = cloth_indices
+            .chunks(12)
+            .flat_map(|quad| quad[0..6].iter().map(|&i| i as u32))
+            .collect();
+        let cloth_triangle_buffer = context.create_buffer(&cloth_wind_triangle_indices, wgpu::BufferUsages::STORAGE);
+
+        //----- TERRAIN -----
+        let terrain_heights = create_terrain_heights(TERRAIN_WIDTH, TERRAIN_DEPTH, TERRAIN_HEIGHT_AMPLITUDE);
+        let terrain_height_buffer = context.create_buffer(&terrain_heights, wgpu::BufferUsages::STORAGE);
 
         //----- COMPUTE -----
         let compute_pipeline = context.create_compute_pipeline("Compute Pipeline", include_str!("compute.wgsl"));
@@ -234,31 +584,79 @@ impl MyApp {
             sphere_position_x: SPHERE_POSITION_X,
             sphere_position_y: SPHERE_POSITION_Y,
             sphere_position_z: SPHERE_POSITION_Z,
+            //implicit solver
+            integration_mode: INTEGRATION_MODE,
+            cg_iterations: CG_ITERATIONS as f32,
+            //self-collision grid
+            grid_cell_size: GRID_CELL_SIZE,
+            self_collision_radius: SELF_COLLISION_RADIUS,
+            //heightfield terrain
+            terrain_origin_x: TERRAIN_ORIGIN_X,
+            terrain_origin_z: TERRAIN_ORIGIN_Z,
+            terrain_cell_size: TERRAIN_CELL_SIZE,
+            terrain_width: TERRAIN_WIDTH as f32,
+            terrain_depth: TERRAIN_DEPTH as f32,
+            terrain_thickness: TERRAIN_THICKNESS,
+            terrain_friction: TERRAIN_FRICTION,
+            //wind
+            nb_cloth_triangles: NB_CLOTH_TRIANGLES,
+            wind_x: WIND_X,
+            wind_y: WIND_Y,
+            wind_z: WIND_Z,
+            air_density: AIR_DENSITY,
+            drag_coefficient: DRAG_COEFFICIENT,
         };
 
         let compute_data_buffer = context.create_buffer(&[compute_data], wgpu::BufferUsages::UNIFORM);
 
-        let compute_vertices_bind_group = context.create_bind_group(
+        // Read-write vertices/velocities for set [i], bound wherever a pass
+        // treats set i as the frame's working ("next") buffer.
+        let compute_vertices_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
             "Compute Vertices Bind Group",
             &compute_pipeline.get_bind_group_layout(0),
             &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: cloth_vertex_buffer.as_entire_binding(),
+                    resource: cloth_vertex_buffers[i].as_entire_binding(),
                 },
             ],
-        );
+        ));
 
-        let compute_vertex_velocities_bind_group = context.create_bind_group(
+        let compute_vertex_velocities_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
             "Compute Vertices Velocities Bind Group",
             &compute_pipeline.get_bind_group_layout(1),
             &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: cloth_vertex_velocity_buffer.as_entire_binding(),
+                    resource: cloth_vertex_velocity_buffers[i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: cloth_vertex_pin_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: terrain_height_buffer.as_entire_binding(),
                 },
             ],
-        );
+        ));
+
+        // Read-only vertices for set [i], bound wherever a pass only reads
+        // positions out of the frame's "current" buffer (never writes it).
+        let compute_springs_vertices_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Compute Springs Vertices Bind Group",
+            &compute_springs_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() }],
+        ));
+
+        // compute_springs.wgsl only declares binding 0 in group(1) (no
+        // pins/terrain), so it needs its own velocities-only group rather
+        // than the 3-binding compute_vertex_velocities_bind_groups.
+        let compute_springs_velocities_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Compute Springs Velocities Bind Group",
+            &compute_springs_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_velocity_buffers[i].as_entire_binding() }],
+        ));
 
         let compute_springs_bind_group = context.create_bind_group(
             "Compute Springs Bind Group",
@@ -271,6 +669,49 @@ impl MyApp {
             ],
         );
 
+        //----- PING-PONG COPY -----
+        // Snapshots set [src] into set [dst] at the top of every frame; see
+        // compute_copy.wgsl.
+        let compute_copy_pipeline = context.create_compute_pipeline("Compute Copy Pipeline", include_str!("compute_copy.wgsl"));
+        let compute_copy_src_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Compute Copy Src Bind Group",
+            &compute_copy_pipeline.get_bind_group_layout(0),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cloth_vertex_velocity_buffers[i].as_entire_binding() },
+            ],
+        ));
+        let compute_copy_dst_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Compute Copy Dst Bind Group",
+            &compute_copy_pipeline.get_bind_group_layout(1),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cloth_vertex_velocity_buffers[i].as_entire_binding() },
+            ],
+        ));
+
+        //----- WIND -----
+        let compute_wind_pipeline = context.create_compute_pipeline("Compute Wind Pipeline", include_str!("compute_wind.wgsl"));
+        let compute_wind_vertices_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Compute Wind Vertices Bind Group",
+            &compute_wind_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() }],
+        ));
+        let compute_wind_triangles_bind_group = context.create_bind_group(
+            "Compute Wind Triangles Bind Group",
+            &compute_wind_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_triangle_buffer.as_entire_binding() }],
+        );
+
+        // compute_wind.wgsl only declares binding 0 in group(1) (no
+        // pins/terrain), so it needs its own velocities-only group rather
+        // than the 3-binding compute_vertex_velocities_bind_groups.
+        let compute_wind_velocities_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Compute Wind Velocities Bind Group",
+            &compute_wind_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_velocity_buffers[i].as_entire_binding() }],
+        ));
+
         let compute_data_bind_group = context.create_bind_group(
             "Compute Data Bind Group",
             &compute_pipeline.get_bind_group_layout(2),
@@ -282,6 +723,251 @@ impl MyApp {
             ],
         );
 
+        //----- IMPLICIT SOLVER (matrix-free conjugate gradient) -----
+        let cg_zero_vecs = vec![CGVec { v: [0.0, 0.0, 0.0] }; NB_CLOTH_VERTICES as usize];
+        let cg_force_buffer = context.create_buffer(&cg_zero_vecs, wgpu::BufferUsages::STORAGE);
+        let cg_r_buffer = context.create_buffer(&cg_zero_vecs, wgpu::BufferUsages::STORAGE);
+        let cg_p_buffer = context.create_buffer(&cg_zero_vecs, wgpu::BufferUsages::STORAGE);
+        let cg_ap_buffer = context.create_buffer(&cg_zero_vecs, wgpu::BufferUsages::STORAGE);
+        let cg_delta_v_buffer = context.create_buffer(&cg_zero_vecs, wgpu::BufferUsages::STORAGE);
+        let cg_scalars_buffer = context.create_buffer(&[CGScalars { rr: 0, pap: 0, rr_new: 0 }], wgpu::BufferUsages::STORAGE);
+
+        let cg_setup_pipeline = context.create_compute_pipeline("CG Setup Pipeline", include_str!("compute_cg_setup.wgsl"));
+        let cg_setup_force_bind_group = context.create_bind_group(
+            "CG Setup Force Bind Group",
+            &cg_setup_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_force_buffer.as_entire_binding() }],
+        );
+        let cg_setup_data_bind_group = context.create_bind_group(
+            "CG Setup Data Bind Group",
+            &cg_setup_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: compute_data_buffer.as_entire_binding() }],
+        );
+
+        let cg_spring_forces_pipeline = context.create_compute_pipeline("CG Spring Forces Pipeline", include_str!("compute_cg_spring_forces.wgsl"));
+        let cg_spring_forces_vertices_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "CG Spring Forces Vertices Bind Group",
+            &cg_spring_forces_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() }],
+        ));
+        let cg_spring_forces_force_bind_group = context.create_bind_group(
+            "CG Spring Forces Force Bind Group",
+            &cg_spring_forces_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_force_buffer.as_entire_binding() }],
+        );
+        let cg_spring_forces_springs_bind_group = context.create_bind_group(
+            "CG Spring Forces Springs Bind Group",
+            &cg_spring_forces_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_spring_buffer.as_entire_binding() }],
+        );
+
+        // h^2*(df/dx)*v, folded into the RHS below on top of h*f.
+        let cg_velocity_term_buffer = context.create_buffer(&cg_zero_vecs, wgpu::BufferUsages::STORAGE);
+
+        let cg_rhs_velocity_setup_pipeline = context.create_compute_pipeline("CG RHS Velocity Setup Pipeline", include_str!("compute_cg_rhs_velocity_setup.wgsl"));
+        let cg_rhs_velocity_setup_term_bind_group = context.create_bind_group(
+            "CG RHS Velocity Setup Term Bind Group",
+            &cg_rhs_velocity_setup_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_velocity_term_buffer.as_entire_binding() }],
+        );
+        let cg_rhs_velocity_setup_data_bind_group = context.create_bind_group(
+            "CG RHS Velocity Setup Data Bind Group",
+            &cg_rhs_velocity_setup_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: compute_data_buffer.as_entire_binding() }],
+        );
+
+        let cg_rhs_velocity_springs_pipeline = context.create_compute_pipeline("CG RHS Velocity Springs Pipeline", include_str!("compute_cg_rhs_velocity_springs.wgsl"));
+        let cg_rhs_velocity_springs_vertices_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "CG RHS Velocity Springs Vertices Bind Group",
+            &cg_rhs_velocity_springs_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() }],
+        ));
+        let cg_rhs_velocity_springs_data_bind_group = context.create_bind_group(
+            "CG RHS Velocity Springs Data Bind Group",
+            &cg_rhs_velocity_springs_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: compute_data_buffer.as_entire_binding() }],
+        );
+        let cg_rhs_velocity_springs_velocities_springs_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "CG RHS Velocity Springs Velocities/Springs Bind Group",
+            &cg_rhs_velocity_springs_pipeline.get_bind_group_layout(2),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_velocity_buffers[i].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cloth_spring_buffer.as_entire_binding() },
+            ],
+        ));
+        let cg_rhs_velocity_springs_term_bind_group = context.create_bind_group(
+            "CG RHS Velocity Springs Term Bind Group",
+            &cg_rhs_velocity_springs_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_velocity_term_buffer.as_entire_binding() }],
+        );
+
+        let cg_rhs_pipeline = context.create_compute_pipeline("CG RHS Pipeline", include_str!("compute_cg_rhs.wgsl"));
+        let cg_rhs_force_bind_group = context.create_bind_group(
+            "CG RHS Force Bind Group",
+            &cg_rhs_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_force_buffer.as_entire_binding() }],
+        );
+        let cg_rhs_vectors_bind_group = context.create_bind_group(
+            "CG RHS Vectors Bind Group",
+            &cg_rhs_pipeline.get_bind_group_layout(2),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cg_r_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cg_p_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: cg_delta_v_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: cloth_vertex_pin_buffer.as_entire_binding() },
+            ],
+        );
+        let cg_rhs_velocity_term_bind_group = context.create_bind_group(
+            "CG RHS Velocity Term Bind Group",
+            &cg_rhs_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_velocity_term_buffer.as_entire_binding() }],
+        );
+
+        let cg_matvec_init_pipeline = context.create_compute_pipeline("CG Matvec Init Pipeline", include_str!("compute_cg_matvec_init.wgsl"));
+        let cg_matvec_init_p_bind_group = context.create_bind_group(
+            "CG Matvec Init P Bind Group",
+            &cg_matvec_init_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_p_buffer.as_entire_binding() }],
+        );
+        let cg_matvec_init_ap_bind_group = context.create_bind_group(
+            "CG Matvec Init Ap Bind Group",
+            &cg_matvec_init_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_ap_buffer.as_entire_binding() }],
+        );
+        let cg_matvec_init_scalars_bind_group = context.create_bind_group(
+            "CG Matvec Init Scalars Bind Group",
+            &cg_matvec_init_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_scalars_buffer.as_entire_binding() }],
+        );
+
+        let cg_matvec_springs_pipeline = context.create_compute_pipeline("CG Matvec Springs Pipeline", include_str!("compute_cg_matvec_springs.wgsl"));
+        let cg_matvec_springs_vertices_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "CG Matvec Springs Vertices Bind Group",
+            &cg_matvec_springs_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() }],
+        ));
+        let cg_matvec_springs_p_springs_bind_group = context.create_bind_group(
+            "CG Matvec Springs P/Springs Bind Group",
+            &cg_matvec_springs_pipeline.get_bind_group_layout(2),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cg_p_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cloth_spring_buffer.as_entire_binding() },
+            ],
+        );
+        let cg_matvec_springs_ap_bind_group = context.create_bind_group(
+            "CG Matvec Springs Ap Bind Group",
+            &cg_matvec_springs_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_ap_buffer.as_entire_binding() }],
+        );
+
+        let cg_dot_pipeline = context.create_compute_pipeline("CG Dot Pipeline", include_str!("compute_cg_dot.wgsl"));
+        let cg_dot_vectors_bind_group = context.create_bind_group(
+            "CG Dot Vectors Bind Group",
+            &cg_dot_pipeline.get_bind_group_layout(0),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cg_r_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cg_p_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: cg_ap_buffer.as_entire_binding() },
+            ],
+        );
+        let cg_dot_scalars_bind_group = context.create_bind_group(
+            "CG Dot Scalars Bind Group",
+            &cg_dot_pipeline.get_bind_group_layout(2),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_scalars_buffer.as_entire_binding() }],
+        );
+
+        let cg_update_pipeline = context.create_compute_pipeline("CG Update Pipeline", include_str!("compute_cg_update.wgsl"));
+        let cg_update_delta_v_r_bind_group = context.create_bind_group(
+            "CG Update Delta V/R Bind Group",
+            &cg_update_pipeline.get_bind_group_layout(0),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cg_delta_v_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cg_r_buffer.as_entire_binding() },
+            ],
+        );
+        let cg_update_p_ap_bind_group = context.create_bind_group(
+            "CG Update P/Ap Bind Group",
+            &cg_update_pipeline.get_bind_group_layout(1),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cg_p_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cg_ap_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: cloth_vertex_pin_buffer.as_entire_binding() },
+            ],
+        );
+        let cg_update_scalars_bind_group = context.create_bind_group(
+            "CG Update Scalars Bind Group",
+            &cg_update_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_scalars_buffer.as_entire_binding() }],
+        );
+
+        let cg_beta_pipeline = context.create_compute_pipeline("CG Beta Pipeline", include_str!("compute_cg_beta.wgsl"));
+        let cg_beta_r_p_bind_group = context.create_bind_group(
+            "CG Beta R/P Bind Group",
+            &cg_beta_pipeline.get_bind_group_layout(0),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: cg_r_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: cg_p_buffer.as_entire_binding() },
+            ],
+        );
+        let cg_beta_scalars_bind_group = context.create_bind_group(
+            "CG Beta Scalars Bind Group",
+            &cg_beta_pipeline.get_bind_group_layout(2),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_scalars_buffer.as_entire_binding() }],
+        );
+
+        let cg_integrate_pipeline = context.create_compute_pipeline("CG Integrate Pipeline", include_str!("compute_cg_integrate.wgsl"));
+        let cg_integrate_delta_v_bind_group = context.create_bind_group(
+            "CG Integrate Delta V Bind Group",
+            &cg_integrate_pipeline.get_bind_group_layout(3),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cg_delta_v_buffer.as_entire_binding() }],
+        );
+
+        //----- SELF-COLLISION (uniform spatial hash grid) -----
+        let self_collision_heads = vec![-1i32; NB_SELF_COLLISION_BUCKETS as usize];
+        let self_collision_next = vec![-1i32; NB_CLOTH_VERTICES as usize];
+        let self_collision_heads_buffer = context.create_buffer(&self_collision_heads, wgpu::BufferUsages::STORAGE);
+        let self_collision_next_buffer = context.create_buffer(&self_collision_next, wgpu::BufferUsages::STORAGE);
+
+        let compute_self_collision_reset_pipeline = context.create_compute_pipeline("Self-Collision Reset Pipeline", include_str!("compute_self_collision_reset.wgsl"));
+        let compute_self_collision_reset_heads_bind_group = context.create_bind_group(
+            "Self-Collision Reset Heads Bind Group",
+            &compute_self_collision_reset_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: self_collision_heads_buffer.as_entire_binding() }],
+        );
+
+        let compute_self_collision_hash_pipeline = context.create_compute_pipeline("Self-Collision Hash Pipeline", include_str!("compute_self_collision_hash.wgsl"));
+        let compute_self_collision_hash_vertices_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Self-Collision Hash Vertices Bind Group",
+            &compute_self_collision_hash_pipeline.get_bind_group_layout(0),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_buffers[i].as_entire_binding() }],
+        ));
+        let compute_self_collision_hash_grid_bind_group = context.create_bind_group(
+            "Self-Collision Hash Grid Bind Group",
+            &compute_self_collision_hash_pipeline.get_bind_group_layout(1),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: self_collision_heads_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: self_collision_next_buffer.as_entire_binding() },
+            ],
+        );
+
+        let compute_self_collision_resolve_pipeline = context.create_compute_pipeline("Self-Collision Resolve Pipeline", include_str!("compute_self_collision_resolve.wgsl"));
+        let compute_self_collision_resolve_grid_bind_group = context.create_bind_group(
+            "Self-Collision Resolve Grid Bind Group",
+            &compute_self_collision_resolve_pipeline.get_bind_group_layout(3),
+            &[
+                wgpu::BindGroupEntry { binding: 0, resource: self_collision_heads_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: self_collision_next_buffer.as_entire_binding() },
+            ],
+        );
+        // compute_self_collision_resolve.wgsl only declares binding 0 in
+        // group(1) (no pins/terrain), so it needs its own velocities-only
+        // group rather than the 3-binding compute_vertex_velocities_bind_groups.
+        let compute_self_collision_resolve_velocities_bind_groups: [wgpu::BindGroup; 2] = std::array::from_fn(|i| context.create_bind_group(
+            "Self-Collision Resolve Velocities Bind Group",
+            &compute_self_collision_resolve_pipeline.get_bind_group_layout(1),
+            &[wgpu::BindGroupEntry { binding: 0, resource: cloth_vertex_velocity_buffers[i].as_entire_binding() }],
+        ));
+
         //----- SPHERE -----
         let sphere_texture = context.create_srgb_texture("bowling_ball.png", include_bytes!("bowling_ball.png"));
         let sphere_diffuse_bind_group = create_texture_bind_group(context, &sphere_texture);
@@ -292,7 +978,8 @@ impl MyApp {
             &[Vertex::desc()],
             &[
                 &context.texture_bind_group_layout,
-                &context.camera_bind_group_layout
+                &context.camera_bind_group_layout,
+                &shadow_bind_group_layout,
             ],
             wgpu::PrimitiveTopology::TriangleList
         );
@@ -315,24 +1002,101 @@ impl MyApp {
             //cloth
             cloth_diffuse_bind_group,
             cloth_pipeline,
-            cloth_vertex_buffer,
+            cloth_vertex_buffers,
+            cloth_vertex_velocity_buffers,
+            current_set: 0,
             cloth_index_buffer,
             nb_cloth_indices: cloth_indices.len(),
             //compute
+            compute_copy_pipeline,
+            compute_copy_src_bind_groups,
+            compute_copy_dst_bind_groups,
             compute_pipeline,
             compute_springs_pipeline,
-            compute_vertices_bind_group,
-            compute_vertex_velocities_bind_group,
+            compute_vertices_bind_groups,
+            compute_vertex_velocities_bind_groups,
+            compute_springs_vertices_bind_groups,
+            compute_springs_velocities_bind_groups,
             compute_springs_bind_group,
             compute_data_bind_group,
             compute_data_buffer,
             compute_data,
+            //wind
+            compute_wind_pipeline,
+            compute_wind_vertices_bind_groups,
+            compute_wind_velocities_bind_groups,
+            compute_wind_triangles_bind_group,
+            wind_phase: 0.0,
+            //implicit solver (matrix-free conjugate gradient)
+            cg_force_buffer,
+            cg_r_buffer,
+            cg_p_buffer,
+            cg_ap_buffer,
+            cg_delta_v_buffer,
+            cg_scalars_buffer,
+            cg_setup_pipeline,
+            cg_setup_force_bind_group,
+            cg_setup_data_bind_group,
+            cg_spring_forces_pipeline,
+            cg_spring_forces_vertices_bind_groups,
+            cg_spring_forces_force_bind_group,
+            cg_spring_forces_springs_bind_group,
+            cg_velocity_term_buffer,
+            cg_rhs_velocity_setup_pipeline,
+            cg_rhs_velocity_setup_term_bind_group,
+            cg_rhs_velocity_setup_data_bind_group,
+            cg_rhs_velocity_springs_pipeline,
+            cg_rhs_velocity_springs_vertices_bind_groups,
+            cg_rhs_velocity_springs_data_bind_group,
+            cg_rhs_velocity_springs_velocities_springs_bind_groups,
+            cg_rhs_velocity_springs_term_bind_group,
+            cg_rhs_pipeline,
+            cg_rhs_force_bind_group,
+            cg_rhs_vectors_bind_group,
+            cg_rhs_velocity_term_bind_group,
+            cg_matvec_init_pipeline,
+            cg_matvec_init_p_bind_group,
+            cg_matvec_init_ap_bind_group,
+            cg_matvec_init_scalars_bind_group,
+            cg_matvec_springs_pipeline,
+            cg_matvec_springs_vertices_bind_groups,
+            cg_matvec_springs_p_springs_bind_group,
+            cg_matvec_springs_ap_bind_group,
+            cg_dot_pipeline,
+            cg_dot_vectors_bind_group,
+            cg_dot_scalars_bind_group,
+            cg_update_pipeline,
+            cg_update_delta_v_r_bind_group,
+            cg_update_p_ap_bind_group,
+            cg_update_scalars_bind_group,
+            cg_beta_pipeline,
+            cg_beta_r_p_bind_group,
+            cg_beta_scalars_bind_group,
+            cg_integrate_pipeline,
+            cg_integrate_delta_v_bind_group,
+            //self-collision (uniform spatial hash grid)
+            self_collision_heads_buffer,
+            self_collision_next_buffer,
+            compute_self_collision_reset_pipeline,
+            compute_self_collision_reset_heads_bind_group,
+            compute_self_collision_hash_pipeline,
+            compute_self_collision_hash_vertices_bind_groups,
+            compute_self_collision_hash_grid_bind_group,
+            compute_self_collision_resolve_pipeline,
+            compute_self_collision_resolve_grid_bind_group,
+            compute_self_collision_resolve_velocities_bind_groups,
             //sphere
             sphere_diffuse_bind_group,
             sphere_pipeline,
             sphere_vertex_buffer,
             sphere_index_buffer,
             nb_sphere_indices: sphere_indices.len(),
+            //shadows
+            shadow_pipeline,
+            shadow_texture,
+            shadow_view,
+            light_bind_group,
+            shadow_bind_group,
         }
     }
 }
@@ -340,6 +1104,38 @@ impl MyApp {
 
 impl Application for MyApp {
     fn render(&self, context: &Context) -> Result<(), wgpu::SurfaceError> {
+        // Shadow pre-pass: depth-only render of the sphere and cloth from the light's
+        // point of view, so the main pass can compare fragment depth against it.
+        {
+            let mut shadow_encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Shadow Pass Encoder"),
+            });
+            {
+                let mut shadow_pass = shadow_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Shadow Pass"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.shadow_view,
+                        depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Store }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                shadow_pass.set_pipeline(&self.shadow_pipeline);
+                shadow_pass.set_bind_group(0, &self.light_bind_group, &[]);
+
+                shadow_pass.set_vertex_buffer(0, self.sphere_vertex_buffer.slice(..));
+                shadow_pass.set_index_buffer(self.sphere_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                shadow_pass.draw_indexed(0..(self.nb_sphere_indices as u32), 0, 0..1);
+
+                shadow_pass.set_vertex_buffer(0, self.cloth_vertex_buffers[self.current_set].slice(..));
+                shadow_pass.set_index_buffer(self.cloth_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                shadow_pass.draw_indexed(0..(self.nb_cloth_indices as u32), 0, 0..1);
+            }
+            context.queue().submit(std::iter::once(shadow_encoder.finish()));
+        }
+
         let mut frame = Frame::new(context)?;
 
         {
@@ -349,6 +1145,7 @@ impl Application for MyApp {
             render_pass.set_pipeline(&self.sphere_pipeline);
             render_pass.set_bind_group(0, &self.sphere_diffuse_bind_group, &[]);
             render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.shadow_bind_group, &[]);
             render_pass.set_vertex_buffer(0, self.sphere_vertex_buffer.slice(..));
             render_pass.set_index_buffer(self.sphere_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
             render_pass.draw_indexed(0..(self.nb_sphere_indices as u32), 0, 0..1);
@@ -357,7 +1154,8 @@ impl Application for MyApp {
             render_pass.set_pipeline(&self.cloth_pipeline);
             render_pass.set_bind_group(0, &self.cloth_diffuse_bind_group, &[]);
             render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.cloth_vertex_buffer.slice(..));
+            render_pass.set_bind_group(2, &self.shadow_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.cloth_vertex_buffers[self.current_set].slice(..));
             render_pass.set_index_buffer(self.cloth_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
             render_pass.draw_indexed(0..(self.nb_cloth_indices as u32), 0, 0..1);
         }
@@ -371,6 +1169,11 @@ impl Application for MyApp {
 
 
     fn update(&mut self, context: &Context, delta_time: f32) {
+        // Gust: modulate the base wind vector with a slow sine so the cloth
+        // flutters instead of settling into a single steady deflection.
+        self.wind_phase += delta_time;
+        let gust = 1.0 + GUST_AMPLITUDE * (self.wind_phase * GUST_FREQUENCY).sin();
+
         // Update the Buffer that contains the delta_time
         let compute_data = ComputeData {
             delta_time,
@@ -388,29 +1191,203 @@ impl Application for MyApp {
             sphere_position_x: SPHERE_POSITION_X,
             sphere_position_y: SPHERE_POSITION_Y,
             sphere_position_z: SPHERE_POSITION_Z,
-        }; 
+            //implicit solver
+            integration_mode: INTEGRATION_MODE,
+            cg_iterations: CG_ITERATIONS as f32,
+            //self-collision grid
+            grid_cell_size: GRID_CELL_SIZE,
+            self_collision_radius: SELF_COLLISION_RADIUS,
+            //heightfield terrain
+            terrain_origin_x: TERRAIN_ORIGIN_X,
+            terrain_origin_z: TERRAIN_ORIGIN_Z,
+            terrain_cell_size: TERRAIN_CELL_SIZE,
+            terrain_width: TERRAIN_WIDTH as f32,
+            terrain_depth: TERRAIN_DEPTH as f32,
+            terrain_thickness: TERRAIN_THICKNESS,
+            terrain_friction: TERRAIN_FRICTION,
+            //wind
+            nb_cloth_triangles: NB_CLOTH_TRIANGLES,
+            wind_x: WIND_X * gust,
+            wind_y: WIND_Y,
+            wind_z: WIND_Z * gust,
+            air_density: AIR_DENSITY,
+            drag_coefficient: DRAG_COEFFICIENT,
+        };
         context.update_buffer(&self.compute_data_buffer, &[compute_data]);
 
 
         let mut computation = Computation::new(context);
 
 
+        let vertex_workgroups = ((NB_CLOTH_VERTICES) as f64 / 64.0).ceil() as u32;
+        let spring_workgroups = ((NB_CLOTH_SPRINGS) as f64 / 64.0).ceil() as u32;
+
+        // Ping-pong: this frame reads last frame's result from `src` and
+        // writes the new state into `dst`, so the renderer (still pointed at
+        // `src` until this function returns) never observes a partially
+        // updated buffer.
+        let src = self.current_set;
+        let dst = 1 - src;
+
         {
+            let mut copy_pass = computation.begin_compute_pass();
+            copy_pass.set_pipeline(&self.compute_copy_pipeline);
+            copy_pass.set_bind_group(0, &self.compute_copy_src_bind_groups[src], &[]);
+            copy_pass.set_bind_group(1, &self.compute_copy_dst_bind_groups[dst], &[]);
+            copy_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            copy_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+        }
+
+        if INTEGRATION_MODE == INTEGRATION_MODE_IMPLICIT {
+            let mut compute_pass = computation.begin_compute_pass();
+
+            // Assemble the force f = gravity + spring forces; the right-hand
+            // side b = h*f + h^2*(df/dx)*v is built from it below.
+            compute_pass.set_pipeline(&self.cg_setup_pipeline);
+            compute_pass.set_bind_group(0, &self.cg_setup_force_bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.cg_setup_data_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+            compute_pass.set_pipeline(&self.cg_spring_forces_pipeline);
+            compute_pass.set_bind_group(0, &self.cg_spring_forces_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.cg_spring_forces_force_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.cg_spring_forces_springs_bind_group, &[]);
+            compute_pass.dispatch_workgroups(spring_workgroups, 1, 1);
+
+            // h^2*(df/dx)*v, folded into b alongside h*f below.
+            compute_pass.set_pipeline(&self.cg_rhs_velocity_setup_pipeline);
+            compute_pass.set_bind_group(0, &self.cg_rhs_velocity_setup_term_bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.cg_rhs_velocity_setup_data_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+            compute_pass.set_pipeline(&self.cg_rhs_velocity_springs_pipeline);
+            compute_pass.set_bind_group(0, &self.cg_rhs_velocity_springs_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.cg_rhs_velocity_springs_data_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.cg_rhs_velocity_springs_velocities_springs_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(3, &self.cg_rhs_velocity_springs_term_bind_group, &[]);
+            compute_pass.dispatch_workgroups(spring_workgroups, 1, 1);
+
+            compute_pass.set_pipeline(&self.cg_rhs_pipeline);
+            compute_pass.set_bind_group(0, &self.cg_rhs_force_bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.compute_data_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.cg_rhs_vectors_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.cg_rhs_velocity_term_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+            // (M - h^2 df/dx) Delta v = b, solved matrix-free with CG.
+            for _ in 0..CG_ITERATIONS {
+                compute_pass.set_pipeline(&self.cg_matvec_init_pipeline);
+                compute_pass.set_bind_group(0, &self.cg_matvec_init_p_bind_group, &[]);
+                compute_pass.set_bind_group(1, &self.cg_matvec_init_ap_bind_group, &[]);
+                compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+                compute_pass.set_bind_group(3, &self.cg_matvec_init_scalars_bind_group, &[]);
+                compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+                compute_pass.set_pipeline(&self.cg_matvec_springs_pipeline);
+                compute_pass.set_bind_group(0, &self.cg_matvec_springs_vertices_bind_groups[dst], &[]);
+                compute_pass.set_bind_group(1, &self.compute_data_bind_group, &[]);
+                compute_pass.set_bind_group(2, &self.cg_matvec_springs_p_springs_bind_group, &[]);
+                compute_pass.set_bind_group(3, &self.cg_matvec_springs_ap_bind_group, &[]);
+                compute_pass.dispatch_workgroups(spring_workgroups, 1, 1);
+
+                compute_pass.set_pipeline(&self.cg_dot_pipeline);
+                compute_pass.set_bind_group(0, &self.cg_dot_vectors_bind_group, &[]);
+                compute_pass.set_bind_group(1, &self.compute_data_bind_group, &[]);
+                compute_pass.set_bind_group(2, &self.cg_dot_scalars_bind_group, &[]);
+                compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+                compute_pass.set_pipeline(&self.cg_update_pipeline);
+                compute_pass.set_bind_group(0, &self.cg_update_delta_v_r_bind_group, &[]);
+                compute_pass.set_bind_group(1, &self.cg_update_p_ap_bind_group, &[]);
+                compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+                compute_pass.set_bind_group(3, &self.cg_update_scalars_bind_group, &[]);
+                compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+                compute_pass.set_pipeline(&self.cg_beta_pipeline);
+                compute_pass.set_bind_group(0, &self.cg_beta_r_p_bind_group, &[]);
+                compute_pass.set_bind_group(1, &self.compute_data_bind_group, &[]);
+                compute_pass.set_bind_group(2, &self.cg_beta_scalars_bind_group, &[]);
+                compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+            }
+
+            compute_pass.set_pipeline(&self.compute_self_collision_reset_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_self_collision_reset_heads_bind_group, &[]);
+            compute_pass.dispatch_workgroups(((NB_SELF_COLLISION_BUCKETS) as f64 / 64.0).ceil() as u32, 1, 1);
+
+            compute_pass.set_pipeline(&self.compute_self_collision_hash_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_self_collision_hash_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.compute_self_collision_hash_grid_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+            compute_pass.set_pipeline(&self.compute_self_collision_resolve_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.compute_self_collision_resolve_velocities_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.compute_self_collision_resolve_grid_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+            // Wind/drag isn't part of the CG force balance above (see
+            // compute_cg_rhs.wgsl); apply it straight to velocities here,
+            // same as the explicit path, before the final integrate step.
+            compute_pass.set_pipeline(&self.compute_wind_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_wind_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.compute_wind_velocities_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.compute_wind_triangles_bind_group, &[]);
+            compute_pass.dispatch_workgroups(((NB_CLOTH_TRIANGLES) as f64 / 64.0).ceil() as u32, 1, 1);
+
+            // v += Delta v; x += h*v (with the same sphere response as the explicit path).
+            compute_pass.set_pipeline(&self.cg_integrate_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.compute_vertex_velocities_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.cg_integrate_delta_v_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+        } else {
             let mut compute_pass = computation.begin_compute_pass();
 
             compute_pass.set_pipeline(&self.compute_springs_pipeline);
-            compute_pass.set_bind_group(0, &self.compute_vertices_bind_group, &[]);
-            compute_pass.set_bind_group(1, &self.compute_vertex_velocities_bind_group, &[]);
+            compute_pass.set_bind_group(0, &self.compute_springs_vertices_bind_groups[src], &[]);
+            compute_pass.set_bind_group(1, &self.compute_springs_velocities_bind_groups[dst], &[]);
             compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
             compute_pass.set_bind_group(3, &self.compute_springs_bind_group, &[]);
-            compute_pass.dispatch_workgroups(((NB_CLOTH_SPRINGS) as f64/64.0).ceil() as u32, 1, 1);
+            compute_pass.dispatch_workgroups(spring_workgroups, 1, 1);
+
+            compute_pass.set_pipeline(&self.compute_wind_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_wind_vertices_bind_groups[src], &[]);
+            compute_pass.set_bind_group(1, &self.compute_wind_velocities_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.compute_wind_triangles_bind_group, &[]);
+            compute_pass.dispatch_workgroups(((NB_CLOTH_TRIANGLES) as f64 / 64.0).ceil() as u32, 1, 1);
+
+            compute_pass.set_pipeline(&self.compute_self_collision_reset_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_self_collision_reset_heads_bind_group, &[]);
+            compute_pass.dispatch_workgroups(((NB_SELF_COLLISION_BUCKETS) as f64 / 64.0).ceil() as u32, 1, 1);
+
+            compute_pass.set_pipeline(&self.compute_self_collision_hash_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_self_collision_hash_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.compute_self_collision_hash_grid_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
+
+            compute_pass.set_pipeline(&self.compute_self_collision_resolve_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.compute_self_collision_resolve_velocities_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.compute_self_collision_resolve_grid_bind_group, &[]);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
 
             compute_pass.set_pipeline(&self.compute_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_vertices_bind_groups[dst], &[]);
+            compute_pass.set_bind_group(1, &self.compute_vertex_velocities_bind_groups[dst], &[]);
             compute_pass.set_bind_group(2, &self.compute_data_bind_group, &[]);
-            compute_pass.dispatch_workgroups(((NB_CLOTH_VERTICES) as f64/64.0).ceil() as u32, 1, 1);
+            compute_pass.dispatch_workgroups(vertex_workgroups, 1, 1);
         }
 
         computation.submit();
+        self.current_set = dst;
     }
 }
 